@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Stream};
+use tokio::timer::Interval;
+
+use crate::error::StreamError;
+use crate::Event;
+
+/// The quiet window rust-analyzer-style editors use before acting on a
+/// filesystem change; long enough to swallow rotation/rewrite bursts without
+/// feeling laggy to a tail -f consumer.
+pub const WATCHER_DELAY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BufferedKind {
+    Write,
+    Create,
+    Delete,
+}
+
+struct Buffered {
+    kind: BufferedKind,
+    last_seen: Instant,
+}
+
+/// Wraps a raw event `Stream` and coalesces bursts for the same path into a
+/// single event, the way `Debouncer` does for rust-analyzer's file watcher.
+///
+/// - repeated `Write`s for one path collapse into one `Write`
+/// - `Create` followed by `Delete` within the window cancels out entirely
+/// - `Delete` followed by `Create` (a rotation) collapses into one `Write`
+///
+/// A path's coalesced event is only emitted once nothing new has arrived for
+/// it for `window`, so a steady stream of distinct paths still flushes on a
+/// rolling basis instead of stalling behind a single noisy file.
+pub struct Debouncer<S> {
+    inner: S,
+    window: Duration,
+    buffer: HashMap<PathBuf, Buffered>,
+    ticker: Interval,
+    done: bool,
+}
+
+impl<S: Stream<Item = Event, Error = StreamError>> Debouncer<S> {
+    pub fn new(inner: S) -> Debouncer<S> {
+        Debouncer::with_window(inner, WATCHER_DELAY)
+    }
+
+    pub fn with_window(inner: S, window: Duration) -> Debouncer<S> {
+        Debouncer {
+            inner,
+            window,
+            buffer: HashMap::new(),
+            ticker: Interval::new_interval(window),
+            done: false,
+        }
+    }
+
+    fn record(&mut self, event: Event) -> Option<Event> {
+        let (path, kind) = match event {
+            Event::Write(p) => (p, BufferedKind::Write),
+            Event::Create(p) => (p, BufferedKind::Create),
+            Event::Delete(p) => (p, BufferedKind::Delete),
+            // Init events represent the pre-existing state, not a burst of
+            // filesystem activity, so they bypass debouncing entirely.
+            init @ Event::Init(_) => return Some(init),
+        };
+
+        let now = Instant::now();
+        match self.buffer.remove(&path) {
+            None => {
+                self.buffer.insert(path, Buffered { kind, last_seen: now });
+            }
+            Some(existing) => match (existing.kind, kind) {
+                (BufferedKind::Create, BufferedKind::Delete) => {
+                    // Created and removed within the window: nothing happened.
+                }
+                (BufferedKind::Delete, BufferedKind::Create) => {
+                    // Rotation: treat the pair as a single rewrite.
+                    self.buffer.insert(path, Buffered { kind: BufferedKind::Write, last_seen: now });
+                }
+                (BufferedKind::Create, BufferedKind::Write) => {
+                    // A freshly created file being written to is still a
+                    // creation as far as a consumer deciding whether to
+                    // start tailing it is concerned; keep `Create`.
+                    self.buffer.insert(path, Buffered { kind: BufferedKind::Create, last_seen: now });
+                }
+                (_, kind) => {
+                    self.buffer.insert(path, Buffered { kind, last_seen: now });
+                }
+            },
+        }
+
+        None
+    }
+
+    fn flush_expired(&mut self) -> Option<Event> {
+        let now = Instant::now();
+        let expired = self.buffer
+            .iter()
+            .find(|(_, buffered)| now.duration_since(buffered.last_seen) >= self.window)
+            .map(|(path, _)| path.clone());
+
+        let path = expired?;
+        let buffered = self.buffer.remove(&path)?;
+        Some(match buffered.kind {
+            BufferedKind::Write => Event::Write(path),
+            BufferedKind::Create => Event::Create(path),
+            BufferedKind::Delete => Event::Delete(path),
+        })
+    }
+}
+
+impl<S: Stream<Item = Event, Error = StreamError>> Stream for Debouncer<S> {
+    type Item = Event;
+    type Error = StreamError;
+
+    fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+        loop {
+            match self.inner.poll()? {
+                Async::Ready(Some(event)) => {
+                    if let Some(passthrough) = self.record(event) {
+                        return Ok(Async::Ready(Some(passthrough)));
+                    }
+                }
+                Async::Ready(None) => {
+                    self.done = true;
+                    break;
+                }
+                Async::NotReady => break,
+            }
+        }
+
+        // Drain the timer so it keeps firing even if we don't act on every tick.
+        while let Async::Ready(_) = self.ticker.poll()? {}
+
+        match self.flush_expired() {
+            Some(event) => Ok(Async::Ready(Some(event))),
+            None if self.done && self.buffer.is_empty() => Ok(Async::Ready(None)),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::thread::sleep;
+
+    struct FakeStream(VecDeque<Event>);
+
+    impl Stream for FakeStream {
+        type Item = Event;
+        type Error = StreamError;
+
+        fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+            Ok(Async::Ready(self.0.pop_front()))
+        }
+    }
+
+    fn collect_until_empty(debouncer: &mut Debouncer<FakeStream>, window: Duration) -> Vec<Event> {
+        // The debouncer's ticker is a `tokio::timer::Interval`, which can only
+        // be polled from inside a runtime that provides a timer.
+        let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+        runtime.block_on(futures::future::lazy(|| {
+            let mut events = Vec::new();
+            loop {
+                match debouncer.poll().unwrap() {
+                    Async::Ready(Some(event)) => events.push(event),
+                    Async::Ready(None) => break,
+                    Async::NotReady => {
+                        if debouncer.done && debouncer.buffer.is_empty() {
+                            break;
+                        }
+                        // Nothing expired yet; give the window time to pass
+                        // before polling again.
+                        sleep(window * 2);
+                    }
+                }
+            }
+            Ok::<_, ()>(events)
+        })).unwrap()
+    }
+
+    fn path(name: &str) -> PathBuf {
+        PathBuf::from(format!("/tmp/{}", name))
+    }
+
+    #[test]
+    fn collapses_repeated_writes() {
+        let events = VecDeque::from(vec![
+            Event::Write(path("a.log")),
+            Event::Write(path("a.log")),
+            Event::Write(path("a.log")),
+        ]);
+        let mut debouncer = Debouncer::with_window(FakeStream(events), Duration::from_millis(10));
+        let out = collect_until_empty(&mut debouncer, Duration::from_millis(10));
+        assert_eq!(out.len(), 1);
+        assert!(matches!(out[0], Event::Write(ref p) if *p == path("a.log")));
+    }
+
+    #[test]
+    fn create_then_delete_cancels_out() {
+        let events = VecDeque::from(vec![
+            Event::Create(path("a.log")),
+            Event::Delete(path("a.log")),
+        ]);
+        let mut debouncer = Debouncer::with_window(FakeStream(events), Duration::from_millis(10));
+        let out = collect_until_empty(&mut debouncer, Duration::from_millis(10));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn delete_then_create_collapses_to_write() {
+        let events = VecDeque::from(vec![
+            Event::Delete(path("a.log")),
+            Event::Create(path("a.log")),
+        ]);
+        let mut debouncer = Debouncer::with_window(FakeStream(events), Duration::from_millis(10));
+        let out = collect_until_empty(&mut debouncer, Duration::from_millis(10));
+        assert_eq!(out.len(), 1);
+        assert!(matches!(out[0], Event::Write(ref p) if *p == path("a.log")));
+    }
+
+    #[test]
+    fn create_then_write_stays_a_create() {
+        let events = VecDeque::from(vec![
+            Event::Create(path("a.log")),
+            Event::Write(path("a.log")),
+        ]);
+        let mut debouncer = Debouncer::with_window(FakeStream(events), Duration::from_millis(10));
+        let out = collect_until_empty(&mut debouncer, Duration::from_millis(10));
+        assert_eq!(out.len(), 1);
+        assert!(matches!(out[0], Event::Create(ref p) if *p == path("a.log")));
+    }
+}
@@ -18,5 +18,8 @@ quick_error! {
          Receive(err: std::sync::mpsc::RecvError) {
              from()
          }
+         Timer(err: tokio::timer::Error) {
+             from()
+         }
      }
 }
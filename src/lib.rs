@@ -1,10 +1,14 @@
 #[macro_use] extern crate log;
 #[macro_use] extern crate quick_error;
 
+use std::mem;
 use std::path::PathBuf;
 
 pub mod rule;
 pub mod error;
+pub mod poll;
+pub mod debounce;
+pub mod priority;
 
 #[cfg(target_os = "macos")]
 mod mac;
@@ -18,6 +22,21 @@ pub type RecommendedWatcher = crate::mac::MacOsWatcher;
 #[cfg(target_os = "linux")]
 pub type RecommendedWatcher = crate::linux::LinuxWatcher;
 
+pub use crate::debounce::Debouncer;
+pub use crate::poll::PollEventStreamer;
+pub use crate::priority::PriorityStream;
+
+/// Common surface implemented by every backend (inotify, FSEvents, polling).
+///
+/// `init` performs the initial glob expansion for a set of patterns, and
+/// `stream` is called repeatedly to drain whatever events have accumulated
+/// since the last call.
+pub trait EventStreamer {
+    fn init(&mut self, patterns: &Vec<&str>) -> ();
+    fn stream(&mut self) -> Vec<Event>;
+    fn watched(&self) -> Vec<PathBuf>;
+}
+
 #[derive(Debug)]
 pub enum Event {
     Create(PathBuf),
@@ -26,6 +45,62 @@ pub enum Event {
     Init(PathBuf),
 }
 
+impl Event {
+    /// Higher values are dequeued first by `PriorityStream`: structural
+    /// changes (`Create`/`Delete`) ahead of `Write`, and `Write` ahead of
+    /// `Init`, so a flood of writes to one file can't starve a rotation.
+    pub fn priority(&self) -> u8 {
+        match self {
+            Event::Create(_) | Event::Delete(_) => 2,
+            Event::Write(_) => 1,
+            Event::Init(_) => 0,
+        }
+    }
+}
+
+/// A backend's snapshot of pre-existing paths, taken at `init()` time and
+/// reported as `Event::Init` before any live event. Every backend (`mac`,
+/// `linux`, `poll`) builds one of these from whatever iterator its initial
+/// glob/stat pass produced, then drains it the same way — this is the one
+/// place that "snapshot now, drain before live events" logic lives, instead
+/// of being reimplemented per backend.
+#[derive(Default)]
+pub(crate) struct InitEvents(Vec<Event>);
+
+impl InitEvents {
+    pub(crate) fn new() -> InitEvents {
+        InitEvents(Vec::new())
+    }
+
+    pub(crate) fn from_paths<I: IntoIterator<Item = PathBuf>>(paths: I) -> InitEvents {
+        InitEvents(paths.into_iter().map(Event::Init).collect())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Drains and returns every queued event, for callers that hand events
+    /// back as a `Vec<Event>` (the synchronous `EventStreamer::stream`
+    /// contract).
+    pub(crate) fn take(&mut self) -> Vec<Event> {
+        mem::replace(&mut self.0, Vec::new())
+    }
+
+    /// Takes ownership of the queued events, leaving an empty queue behind;
+    /// used when handing the snapshot off to an async `Stream` wrapper that
+    /// owns its own queue.
+    pub(crate) fn drain(&mut self) -> InitEvents {
+        InitEvents(mem::replace(&mut self.0, Vec::new()))
+    }
+
+    /// Pops one event at a time, for callers that hand events back one at a
+    /// time (the async `Stream::poll` contract).
+    pub(crate) fn pop(&mut self) -> Option<Event> {
+        self.0.pop()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use futures::future;
@@ -4,28 +4,55 @@ extern crate lazy_static;
 
 use std::ffi::OsStr;
 use std::fs::read_link;
+use std::mem;
 use std::path::PathBuf;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
-use super::{Event};
+use super::{Event, EventStreamer, InitEvents};
+use crate::debounce::Debouncer;
+use crate::error::{StreamError, WatchError};
+use crate::priority::PriorityStream;
+use crate::rule::Rules;
 
+use futures::{Async, Stream, try_ready};
 use glob::glob;
 use hashbrown::HashMap;
 use inotify::{
     Event as RawEvent, EventMask, Inotify, WatchDescriptor, WatchMask,
 };
+use tokio_threadpool::blocking;
 
 lazy_static! {
     static ref DIR_WATCH_MASK: WatchMask = WatchMask::CREATE |  WatchMask::DELETE;
     static ref FILE_WATCH_MASK: WatchMask =   WatchMask::MOVE_SELF | WatchMask::MODIFY;
 }
 
+/// Bundles the `Rules` a `LinuxEventStreamer` filters paths through. Kept as
+/// its own type so the streamer's construction mirrors the other backends'
+/// `new(rules)` shape while leaving room for inotify-specific options later.
+#[derive(Debug)]
+pub struct Options {
+    rules: Rules,
+}
+
+impl Options {
+    pub fn new(rules: Rules) -> Options {
+        Options { rules }
+    }
+}
+
+fn check_path(options: &Options, path: &PathBuf) -> bool {
+    options.rules.matches(path)
+}
+
 pub struct LinuxEventStreamer {
     watch_descriptor_map: HashMap<WatchDescriptor, PathBuf>,
     path_map: HashMap<PathBuf, WatchDescriptor>,
+    watched_dirs: Vec<String>,
     inotify: Inotify,
     options: Options,
+    pending_init: InitEvents,
 }
 
 impl LinuxEventStreamer {
@@ -33,22 +60,24 @@ impl LinuxEventStreamer {
         LinuxEventStreamer {
             watch_descriptor_map: HashMap::new(),
             path_map: HashMap::new(),
+            watched_dirs: Vec::new(),
             inotify: Inotify::init().unwrap(),
             options,
+            pending_init: InitEvents::new(),
         }
     }
 
     fn handle_create(&mut self, path: &PathBuf, raw_event: RawEvent<&OsStr>, events: &mut Vec<Event>) {
         if let Some(path) = raw_event.name.map(|s| path.join(s)) {
             if !raw_event.mask.contains(EventMask::ISDIR) {
-                if let Some(s) = path.to_str() { self.add(s) }
+                if let Some(s) = path.to_str() { self.watch(s) }
                 if check_path(&self.options, &path) {
                     events.push(Event::Create(path));
                 }
             } else {
-                if let Some(s) = path.to_str() { self.add(s) }
+                if let Some(s) = path.to_str() { self.watch(s) }
                 if let Some(s) = path.join("**/*").to_str() {
-                    self.add(s);
+                    self.watch(s);
                     match glob(s) {
                         Ok(paths) => paths.filter_map(|r| r.ok())
                             .filter(|p| p.is_file())
@@ -111,13 +140,26 @@ impl LinuxEventStreamer {
             sleep(interval)
         }
 
-        if let Some(s) = path.to_str() { self.add(s) }
+        if let Some(s) = path.to_str() { self.watch(s) }
         if check_path(&self.options, &path) {
             events.push(Event::Create(path));
         }
     }
 
+    /// Registers `pattern` as a watch root: recorded in `watched_dirs` so
+    /// `rescan()` can re-glob it later, then immediately established via
+    /// `watch()`. Call this for patterns the caller itself asked to watch;
+    /// paths discovered afterwards (new files under a watched directory, a
+    /// rotated-in replacement, a rescan hit) should call `watch()` directly
+    /// so `watched_dirs` doesn't grow with every path churn.
     pub fn add(&mut self, pattern: &str) {
+        self.watched_dirs.push(pattern.to_string());
+        self.watch(pattern);
+    }
+
+    /// Establishes an inotify watch for every path `pattern` globs to,
+    /// without recording `pattern` as a root for `rescan()`.
+    fn watch(&mut self, pattern: &str) {
         let paths = match glob(pattern) {
             Ok(v) => v,
             Err(e) => {
@@ -170,6 +212,63 @@ impl LinuxEventStreamer {
             info!("now watching ({},{}) items", self.path_map.len(), self.watch_descriptor_map.len());
         }
     }
+
+    /// Re-globs every watched root and diffs the result against `path_map`,
+    /// emitting synthetic creates/deletes for whatever changed while we
+    /// weren't looking. Used to recover from an `IN_Q_OVERFLOW`, where the
+    /// kernel queue dropped events and the watcher fell out of sync.
+    fn rescan(&mut self, events: &mut Vec<Event>) {
+        let mut seen = HashMap::with_capacity(self.path_map.len());
+
+        // Reuse the exact pattern handed to `add()` (which may already be a
+        // glob like `/var/log/*.log`, not a bare directory) instead of
+        // appending "/**/*" to it, which would mangle it into something
+        // that matches nothing on disk.
+        let watched_dirs = self.watched_dirs.clone();
+        for dir in &watched_dirs {
+            let paths = match glob(dir) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("rescan glob error {:?}: {:?}", &dir, &e);
+                    continue;
+                }
+            };
+
+            for path in paths.filter_map(|r| r.ok()) {
+                if !check_path(&self.options, &path) {
+                    continue;
+                }
+
+                if path.is_dir() {
+                    if !self.path_map.contains_key(&path) {
+                        if let Some(s) = path.to_str() { self.watch(s); }
+                    }
+                    seen.insert(path, ());
+                    continue;
+                }
+
+                if !self.path_map.contains_key(&path) {
+                    if let Some(s) = path.to_str() { self.watch(s); }
+                    events.push(Event::Create(path.clone()));
+                }
+
+                seen.insert(path, ());
+            }
+        }
+
+        let missing: Vec<PathBuf> = self.path_map.keys()
+            .filter(|p| !seen.contains_key(*p) && !p.exists())
+            .cloned()
+            .collect();
+
+        for path in missing {
+            if let Some(wd) = self.path_map.get(&path).cloned() {
+                self.watch_descriptor_map.remove(&wd);
+            }
+            self.path_map.remove(&path);
+            events.push(Event::Delete(path));
+        }
+    }
 }
 
 impl EventStreamer for LinuxEventStreamer {
@@ -177,9 +276,17 @@ impl EventStreamer for LinuxEventStreamer {
         for pattern in patterns {
             self.add(pattern);
         }
+
+        // Snapshot what the initial glob expansion found so the first
+        // `stream()` call reports it as `Event::Init` before any live event.
+        self.pending_init = InitEvents::from_paths(self.path_map.keys().cloned());
     }
 
     fn stream(&mut self) -> Vec<Event> {
+        if !self.pending_init.is_empty() {
+            return self.pending_init.take();
+        }
+
         let mut events = Vec::new();
 
         let mut buff = [0u8; 8_192];
@@ -192,6 +299,12 @@ impl EventStreamer for LinuxEventStreamer {
         };
 
         for raw_event in raw_events {
+            if raw_event.mask.contains(EventMask::Q_OVERFLOW) {
+                warn!("inotify queue overflowed, rescanning watched directories");
+                self.rescan(&mut events);
+                continue;
+            }
+
             if let Some(path) = self.watch_descriptor_map.get(&raw_event.wd).map(Clone::clone) {
                 if raw_event.mask.contains(EventMask::CREATE) {
                     self.handle_create(&path, raw_event, &mut events);
@@ -218,3 +331,149 @@ impl EventStreamer for LinuxEventStreamer {
         watched
     }
 }
+
+/// Public, platform-uniform handle for the Linux backend. Presents the same
+/// `new`/`add`/`init`/`watched`/`stream` contract as `MacOsWatcher` so
+/// `RecommendedWatcher` is portable across platforms.
+pub struct LinuxWatcher {
+    streamer: LinuxEventStreamer,
+    initialized: bool,
+}
+
+impl LinuxWatcher {
+    pub fn new(rules: Rules) -> LinuxWatcher {
+        LinuxWatcher {
+            streamer: LinuxEventStreamer::new(Options::new(rules)),
+            initialized: false,
+        }
+    }
+
+    pub fn add<T: Into<PathBuf>>(&mut self, path: T) -> Result<(), WatchError> {
+        let true_path = path.into();
+        if !check_path(&self.streamer.options, &true_path) {
+            return Err(WatchError::Excluded(format!("{:?} has been excluded", &true_path)));
+        }
+
+        let true_path_str = match true_path.to_str() {
+            Some(v) => v.to_string(),
+            None => {
+                return Err(WatchError::InvalidPath(format!("{:?} is an invalid path", &true_path)));
+            },
+        };
+
+        self.streamer.add(&true_path_str);
+        Ok(())
+    }
+
+    pub fn init(&mut self) -> () {
+        if self.initialized {
+            warn!("inotify watcher has already been initialized");
+            return;
+        }
+
+        // Snapshot what's already watched so the stream can report it as
+        // `Event::Init` before any live event. This reuses the same
+        // bookkeeping `EventStreamer::init` does, so callers going through
+        // either `LinuxWatcher` or `LinuxEventStreamer` directly see
+        // consistent `Init` behavior.
+        self.streamer.pending_init = InitEvents::from_paths(self.streamer.watched());
+        self.initialized = true;
+    }
+
+    pub fn watched(&self) -> Vec<PathBuf> {
+        self.streamer.watched()
+    }
+
+    pub fn stream(self) -> impl Stream<Item=Event, Error=StreamError> {
+        let mut streamer = self.streamer;
+        let init_events = streamer.pending_init.drain();
+        Debouncer::new(PriorityStream::new(LinuxEventStream {
+            streamer,
+            events: Vec::new(),
+            init_events,
+        }))
+    }
+}
+
+pub struct LinuxEventStream {
+    streamer: LinuxEventStreamer,
+    events: Vec<Event>,
+    init_events: InitEvents,
+}
+
+impl Stream for LinuxEventStream {
+    type Item = Event;
+    type Error = StreamError;
+
+    fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+        if let Some(event) = self.init_events.pop() {
+            return Ok(Async::Ready(Some(event)));
+        }
+
+        while self.events.is_empty() {
+            // Rebuild our empty vector to resize any allocated space
+            mem::replace(&mut self.events, Vec::new());
+
+            self.events = try_ready!(blocking(|| self.streamer.stream()))?;
+        }
+
+        match self.events.pop() {
+            Some(e) => Ok(Async::Ready(Some(e))),
+            None => {
+                // Not sure how we got here
+                self.poll()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("log-events-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rescan_diffs_created_and_deleted_files() {
+        let dir = scratch_dir("rescan");
+        let kept = dir.join("kept.log");
+        let removed = dir.join("removed.log");
+        File::create(&kept).unwrap();
+        File::create(&removed).unwrap();
+
+        let pattern = format!("{}/*.log", dir.display());
+        let mut streamer = LinuxEventStreamer::new(Options::new(Rules::new()));
+        streamer.add(&pattern);
+
+        assert!(streamer.path_map.contains_key(&kept));
+        assert!(streamer.path_map.contains_key(&removed));
+
+        fs::remove_file(&removed).unwrap();
+        let created = dir.join("created.log");
+        File::create(&created).unwrap();
+
+        let mut events = Vec::new();
+        streamer.rescan(&mut events);
+
+        let creates: Vec<&PathBuf> = events.iter()
+            .filter_map(|e| match e { Event::Create(p) => Some(p), _ => None })
+            .collect();
+        let deletes: Vec<&PathBuf> = events.iter()
+            .filter_map(|e| match e { Event::Delete(p) => Some(p), _ => None })
+            .collect();
+
+        assert_eq!(creates, vec![&created]);
+        assert_eq!(deletes, vec![&removed]);
+        assert!(streamer.path_map.contains_key(&kept));
+        assert!(!streamer.path_map.contains_key(&removed));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,9 +3,11 @@ use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread;
 use std::mem;
 
-use crate::Event;
+use crate::{Event, InitEvents};
 use crate::rule::Rules;
 use crate::error::{WatchError, StreamError};
+use crate::debounce::Debouncer;
+use crate::priority::PriorityStream;
 
 use futures::{Async, Stream, try_ready};
 use hashbrown::HashSet;
@@ -20,6 +22,7 @@ pub struct MacOsWatcher {
     fsevent_receiver: Receiver<fsevent::Event>,
     fsevent_thread: Option<thread::JoinHandle<()>>, // Probably should find to do this with tokio. Will do it next time...
     rules: Rules,
+    pending_init: InitEvents,
 }
 
 impl MacOsWatcher {
@@ -33,14 +36,17 @@ impl MacOsWatcher {
             fsevent_receiver: receiver,
             fsevent_thread: None,
             rules: rules,
+            pending_init: InitEvents::new(),
         }
     }
 
-    pub fn stream(self) -> impl Stream<Item=Event, Error=StreamError> {
-        MacOsEventStream {
+    pub fn stream(mut self) -> impl Stream<Item=Event, Error=StreamError> {
+        let init_events = self.pending_init.drain();
+        Debouncer::new(PriorityStream::new(MacOsEventStream {
             watcher: self,
-            events: Vec::new()
-        }
+            events: Vec::new(),
+            init_events,
+        }))
     }
 
     pub fn watched(&self) -> Vec<PathBuf> {
@@ -99,6 +105,10 @@ impl MacOsWatcher {
             return;
         }
 
+        // Snapshot what the initial glob expansion found so the stream can
+        // report it as `Event::Init` before any live event.
+        self.pending_init = InitEvents::from_paths(self.watched_files.iter().cloned());
+
         let sender_clone = self.fsevent_sender.clone();
         let watched_dirs_clone = self.watched_dirs.clone();
         self.fsevent_thread = Some(thread::spawn(move || {
@@ -149,6 +159,7 @@ impl MacOsWatcher {
 pub struct MacOsEventStream {
     watcher: MacOsWatcher,
     events: Vec<Event>,
+    init_events: InitEvents,
 }
 
 impl Stream for MacOsEventStream {
@@ -156,6 +167,10 @@ impl Stream for MacOsEventStream {
     type Error = StreamError;
 
     fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+        if let Some(event) = self.init_events.pop() {
+            return Ok(Async::Ready(Some(event)));
+        }
+
         while self.events.is_empty() {
             // Rebuild our empty vector to resize any allocated space
             mem::replace(&mut self.events, Vec::new());
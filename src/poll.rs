@@ -0,0 +1,251 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use futures::{Async, Stream};
+use glob::glob;
+use hashbrown::HashMap;
+use tokio::timer::Interval;
+
+use crate::debounce::Debouncer;
+use crate::error::StreamError;
+use crate::priority::PriorityStream;
+use crate::rule::Rules;
+use crate::{Event, EventStreamer, InitEvents};
+
+/// How often `PollEventStreamer` re-globs and re-stats watched files by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An `EventStreamer` for filesystems and platforms without a native
+/// notification API (NFS/CIFS mounts, containers without inotify/FSEvents).
+///
+/// Instead of subscribing to kernel events, it keeps a snapshot of every
+/// watched file's last-modified time and size, and diffs a fresh snapshot
+/// against it on each `stream()` call.
+pub struct PollEventStreamer {
+    patterns: Vec<String>,
+    snapshot: HashMap<PathBuf, (SystemTime, u64)>,
+    rules: Rules,
+    interval: Duration,
+    pending_init: InitEvents,
+}
+
+impl PollEventStreamer {
+    pub fn new(rules: Rules) -> PollEventStreamer {
+        PollEventStreamer::with_interval(rules, DEFAULT_POLL_INTERVAL)
+    }
+
+    pub fn with_interval(rules: Rules, interval: Duration) -> PollEventStreamer {
+        PollEventStreamer {
+            patterns: Vec::new(),
+            snapshot: HashMap::new(),
+            rules,
+            interval,
+            pending_init: InitEvents::new(),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn add(&mut self, pattern: &str) {
+        self.patterns.push(pattern.to_string());
+        for path in self.expand(pattern) {
+            self.stat_into_snapshot(&path);
+        }
+    }
+
+    fn expand(&self, pattern: &str) -> Vec<PathBuf> {
+        let paths = match glob(pattern) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("glob error: {:?}", &e);
+                return Vec::new();
+            }
+        };
+
+        paths.filter_map(|r| r.ok())
+            .filter(|p| p.is_file())
+            .filter(|p| self.rules.matches(p))
+            .collect()
+    }
+
+    fn stat_into_snapshot(&mut self, path: &PathBuf) {
+        if let Ok(metadata) = path.metadata() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            self.snapshot.insert(path.clone(), (modified, metadata.len()));
+        }
+    }
+
+    fn rescan(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut seen = HashMap::with_capacity(self.snapshot.len());
+
+        let patterns = self.patterns.clone();
+        for pattern in &patterns {
+            for path in self.expand(pattern) {
+                let stat = match path.metadata() {
+                    Ok(metadata) => {
+                        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                        (modified, metadata.len())
+                    }
+                    Err(_) => {
+                        // Disappeared between the glob expansion and the stat; treat as deleted.
+                        continue;
+                    }
+                };
+
+                match self.snapshot.get(&path) {
+                    None => events.push(Event::Create(path.clone())),
+                    Some(previous) if *previous != stat => events.push(Event::Write(path.clone())),
+                    Some(_) => {}
+                }
+
+                seen.insert(path, stat);
+            }
+        }
+
+        for (path, _) in self.snapshot.iter() {
+            if !seen.contains_key(path) {
+                events.push(Event::Delete(path.clone()));
+            }
+        }
+
+        self.snapshot = seen;
+        events
+    }
+
+    /// Wraps this streamer in a `tokio::timer::Interval`-paced `Stream`, the
+    /// same shape `MacOsWatcher::stream` and `LinuxWatcher::stream` expose,
+    /// so callers don't have to drive `rescan()` themselves. `interval`
+    /// governs how often the poller re-globs and re-stats watched files.
+    pub fn stream(mut self) -> impl Stream<Item=Event, Error=StreamError> {
+        let ticker = Interval::new_interval(self.interval);
+        let init_events = self.pending_init.drain();
+        Debouncer::new(PriorityStream::new(PollEventStream {
+            streamer: self,
+            ticker,
+            events: Vec::new(),
+            init_events,
+        }))
+    }
+}
+
+impl EventStreamer for PollEventStreamer {
+    fn init(&mut self, patterns: &Vec<&str>) -> () {
+        for pattern in patterns {
+            self.add(pattern);
+        }
+
+        // Snapshot what the initial glob expansion found so the first
+        // `stream()` call reports it as `Event::Init` before any live event.
+        self.pending_init = InitEvents::from_paths(self.snapshot.keys().cloned());
+    }
+
+    fn stream(&mut self) -> Vec<Event> {
+        if !self.pending_init.is_empty() {
+            return self.pending_init.take();
+        }
+
+        self.rescan()
+    }
+
+    fn watched(&self) -> Vec<PathBuf> {
+        self.snapshot.keys().cloned().collect()
+    }
+}
+
+pub struct PollEventStream {
+    streamer: PollEventStreamer,
+    ticker: Interval,
+    events: Vec<Event>,
+    init_events: InitEvents,
+}
+
+impl Stream for PollEventStream {
+    type Item = Event;
+    type Error = StreamError;
+
+    fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+        if let Some(event) = self.init_events.pop() {
+            return Ok(Async::Ready(Some(event)));
+        }
+
+        while self.events.is_empty() {
+            match self.ticker.poll()? {
+                Async::Ready(Some(_)) => {
+                    self.events = self.streamer.rescan();
+                }
+                Async::Ready(None) => return Ok(Async::Ready(None)),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+
+        match self.events.pop() {
+            Some(e) => Ok(Async::Ready(Some(e))),
+            None => {
+                // Not sure how we got here
+                self.poll()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::io::Write;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("log-events-test-poll-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rescan_diffs_created_written_and_deleted_files() {
+        let dir = scratch_dir("rescan");
+        let kept = dir.join("kept.log");
+        let removed = dir.join("removed.log");
+        File::create(&kept).unwrap();
+        File::create(&removed).unwrap();
+
+        let pattern = format!("{}/*.log", dir.display());
+        let mut streamer = PollEventStreamer::new(Rules::new());
+        streamer.add(&pattern);
+
+        assert!(streamer.snapshot.contains_key(&kept));
+        assert!(streamer.snapshot.contains_key(&removed));
+
+        fs::remove_file(&removed).unwrap();
+        let created = dir.join("created.log");
+        File::create(&created).unwrap();
+        {
+            let mut f = fs::OpenOptions::new().append(true).open(&kept).unwrap();
+            f.write_all(b"more data").unwrap();
+        }
+
+        let events = streamer.rescan();
+
+        let creates: Vec<&PathBuf> = events.iter()
+            .filter_map(|e| match e { Event::Create(p) => Some(p), _ => None })
+            .collect();
+        let writes: Vec<&PathBuf> = events.iter()
+            .filter_map(|e| match e { Event::Write(p) => Some(p), _ => None })
+            .collect();
+        let deletes: Vec<&PathBuf> = events.iter()
+            .filter_map(|e| match e { Event::Delete(p) => Some(p), _ => None })
+            .collect();
+
+        assert_eq!(creates, vec![&created]);
+        assert_eq!(writes, vec![&kept]);
+        assert_eq!(deletes, vec![&removed]);
+        assert!(streamer.snapshot.contains_key(&kept));
+        assert!(!streamer.snapshot.contains_key(&removed));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
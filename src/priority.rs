@@ -0,0 +1,144 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::path::PathBuf;
+
+use futures::{Async, Stream};
+
+use crate::error::StreamError;
+use crate::Event;
+
+/// How many events `PriorityStream` will hold before it starts dropping the
+/// lowest-priority, oldest entry to make room for a new one.
+pub const DEFAULT_CAPACITY: usize = 4_096;
+
+struct Queued {
+    priority: u8,
+    sequence: u64,
+    event: Event,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Higher priority first; within the same priority, earlier arrivals
+        // (lower sequence) come out first, so compare it in reverse.
+        self.priority.cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+/// Reorders a raw event `Stream` so structural changes (`Create`/`Delete`)
+/// are dequeued ahead of `Write`, and `Write` ahead of `Init`, the way
+/// watchexec's priority-channel fs worker keeps rename/rotation handling
+/// responsive under a flood of writes to one noisy file.
+///
+/// Bounded: once `capacity` is reached, the lowest-priority, oldest queued
+/// `Create`/`Write`/`Delete` event is dropped to make room rather than
+/// growing without bound. `Init` events represent a one-time snapshot of
+/// pre-existing state rather than live churn, so they bypass the bounded
+/// heap entirely through an unbounded FIFO queue and are never evicted; an
+/// initial scan of more than `capacity` files would otherwise silently lose
+/// some of them before a consumer ever saw them.
+pub struct PriorityStream<S> {
+    inner: S,
+    heap: BinaryHeap<Queued>,
+    init_queue: VecDeque<PathBuf>,
+    capacity: usize,
+    next_sequence: u64,
+    done: bool,
+}
+
+impl<S: Stream<Item = Event, Error = StreamError>> PriorityStream<S> {
+    pub fn new(inner: S) -> PriorityStream<S> {
+        PriorityStream::with_capacity(inner, DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(inner: S, capacity: usize) -> PriorityStream<S> {
+        PriorityStream {
+            inner,
+            heap: BinaryHeap::new(),
+            init_queue: VecDeque::new(),
+            capacity,
+            next_sequence: 0,
+            done: false,
+        }
+    }
+
+    fn push(&mut self, event: Event) {
+        let event = match event {
+            Event::Init(path) => {
+                self.init_queue.push_back(path);
+                return;
+            }
+            other => other,
+        };
+
+        if self.heap.len() >= self.capacity {
+            warn!("priority queue at capacity ({}), dropping oldest low-priority event", self.capacity);
+            let mut items = std::mem::replace(&mut self.heap, BinaryHeap::new()).into_vec();
+            // `Queued`'s `Ord` orders by dequeue order (priority, then FIFO
+            // within a tier), which is the wrong axis for picking an
+            // eviction target: its "smallest" element is the *newest*
+            // low-priority arrival. Compare priority then sequence directly
+            // instead, so the lowest-priority, oldest entry is evicted.
+            let worst = items.iter().enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    a.priority.cmp(&b.priority).then_with(|| a.sequence.cmp(&b.sequence))
+                })
+                .map(|(i, _)| i);
+            if let Some(worst) = worst {
+                items.remove(worst);
+            }
+            self.heap = items.into_iter().collect();
+        }
+
+        let queued = Queued {
+            priority: event.priority(),
+            sequence: self.next_sequence,
+            event,
+        };
+        self.next_sequence += 1;
+        self.heap.push(queued);
+    }
+}
+
+impl<S: Stream<Item = Event, Error = StreamError>> Stream for PriorityStream<S> {
+    type Item = Event;
+    type Error = StreamError;
+
+    fn poll(&mut self) -> Result<Async<Option<Self::Item>>, Self::Error> {
+        loop {
+            match self.inner.poll()? {
+                Async::Ready(Some(event)) => self.push(event),
+                Async::Ready(None) => {
+                    self.done = true;
+                    break;
+                }
+                Async::NotReady => break,
+            }
+        }
+
+        if let Some(queued) = self.heap.pop() {
+            return Ok(Async::Ready(Some(queued.event)));
+        }
+
+        match self.init_queue.pop_front() {
+            Some(path) => Ok(Async::Ready(Some(Event::Init(path)))),
+            None if self.done => Ok(Async::Ready(None)),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
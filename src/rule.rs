@@ -1,7 +1,9 @@
 use glob::Pattern;
 use regex::Regex;
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
 pub trait Rule<T>: Debug {
     fn matches(&self, item: &T) -> bool;
@@ -37,6 +39,92 @@ impl Rule<PathBuf> for GlobRule {
     }
 }
 
+#[derive(Debug)]
+struct IgnorePattern {
+    pattern: Pattern,
+    negate: bool,
+}
+
+/// A `.gitignore`-style rule loaded from an ignore file: patterns are
+/// evaluated in file order so a later negation (`!pattern`) can override an
+/// earlier exclusion, same as git does.
+#[derive(Debug)]
+pub struct IgnoreFileRule {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreFileRule {
+    pub fn load(path: &PathBuf) -> io::Result<IgnoreFileRule> {
+        let contents = fs::read_to_string(path)?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut patterns = Vec::new();
+
+        for line in contents.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (negate, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            let (anchored, line) = match line.strip_prefix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            let glob_str = if anchored {
+                format!("{}/{}", base.display(), line)
+            } else {
+                format!("{}/**/{}", base.display(), line)
+            };
+
+            match Pattern::new(&glob_str) {
+                Ok(pattern) => patterns.push(IgnorePattern { pattern, negate }),
+                Err(e) => {
+                    error!("invalid ignore pattern {:?} in {:?}: {:?}", line, path, e);
+                    continue;
+                }
+            }
+
+            // A directory-only pattern also excludes everything beneath it.
+            if dir_only {
+                if let Ok(pattern) = Pattern::new(&format!("{}/**", glob_str)) {
+                    patterns.push(IgnorePattern { pattern, negate });
+                }
+            }
+        }
+
+        Ok(IgnoreFileRule { patterns })
+    }
+}
+
+impl Rule<PathBuf> for IgnoreFileRule {
+    fn matches(&self, item: &PathBuf) -> bool {
+        let s = match item.to_str() {
+            Some(s) => s,
+            None => return false,
+        };
+
+        // Later patterns win, so a trailing negation can re-include a path
+        // an earlier pattern excluded.
+        let mut ignored = false;
+        for pattern in &self.patterns {
+            if pattern.pattern.matches(s) {
+                ignored = !pattern.negate;
+            }
+        }
+        ignored
+    }
+}
+
 #[derive(Debug)]
 pub struct Rules {
     include: Vec<Box<Rule<PathBuf> + Send>>,
@@ -50,6 +138,12 @@ impl Rules {
             exclude: Vec::new(),
         }
     }
+    pub fn add_ignore_file<T: Into<PathBuf>>(&mut self, path: T) -> io::Result<()> {
+        let rule = IgnoreFileRule::load(&path.into())?;
+        self.exclude.push(Box::new(rule));
+        Ok(())
+    }
+
     pub fn matches(&self, item: &PathBuf) -> bool {
         for include_rule in &self.include {
             if !include_rule.matches(item) {
@@ -65,3 +159,56 @@ impl Rules {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("log-events-test-rule-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn later_negation_overrides_earlier_exclusion() {
+        let dir = scratch_dir("negate");
+        let ignore_path = dir.join(".logdnaignore");
+        fs::write(&ignore_path, "*.log\n!keep.log\n").unwrap();
+
+        let rule = IgnoreFileRule::load(&ignore_path).unwrap();
+        assert!(rule.matches(&dir.join("drop.log")));
+        assert!(!rule.matches(&dir.join("keep.log")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leading_slash_anchors_to_the_ignore_files_directory() {
+        let dir = scratch_dir("anchor");
+        let ignore_path = dir.join(".logdnaignore");
+        fs::write(&ignore_path, "/only-here.log\n").unwrap();
+
+        let rule = IgnoreFileRule::load(&ignore_path).unwrap();
+        assert!(rule.matches(&dir.join("only-here.log")));
+        assert!(!rule.matches(&dir.join("nested").join("only-here.log")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn trailing_slash_excludes_the_directory_and_its_contents_only() {
+        let dir = scratch_dir("dironly");
+        let ignore_path = dir.join(".logdnaignore");
+        fs::write(&ignore_path, "build/\n").unwrap();
+
+        let rule = IgnoreFileRule::load(&ignore_path).unwrap();
+        assert!(rule.matches(&dir.join("build").join("output.log")));
+        assert!(!rule.matches(&dir.join("build.log")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+